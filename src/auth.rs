@@ -0,0 +1,42 @@
+use hyper::header::Authorization;
+
+/// How the client proves its identity to the forge.
+///
+/// Bitbucket Server/Cloud both accept HTTP Basic (a base64-encoded
+/// `username:password`), but newer deployments issue personal / HTTP access
+/// tokens that are meant to travel as `Authorization: Bearer <token>`. The
+/// config holds whichever form the user set up and the client turns it into
+/// the matching header.
+#[derive(Debug, Clone)]
+pub enum Auth {
+    /// base64-encoded `username:password`, sent as HTTP Basic.
+    Basic(String),
+    /// A personal / HTTP access token, sent as a Bearer credential.
+    Bearer(String),
+}
+
+impl Auth {
+    /// Build the `Authorization` header value for this credential.
+    pub fn header(&self) -> Authorization<String> {
+        match *self {
+            Auth::Basic(ref encoded) => Authorization(format!("Basic {}", encoded)),
+            Auth::Bearer(ref token) => Authorization(format!("Bearer {}", token)),
+        }
+    }
+
+    /// The stored secret, i.e. the value that gets encrypted at rest.
+    pub fn secret(&self) -> &str {
+        match *self {
+            Auth::Basic(ref encoded) => encoded,
+            Auth::Bearer(ref token) => token,
+        }
+    }
+
+    /// Reconstruct an `Auth` from a recovered `secret`, preserving its scheme.
+    pub fn with_secret(&self, secret: String) -> Auth {
+        match *self {
+            Auth::Basic(_) => Auth::Basic(secret),
+            Auth::Bearer(_) => Auth::Bearer(secret),
+        }
+    }
+}
@@ -2,13 +2,15 @@ use std::io::Read;
 
 use hyper::Client;
 use hyper::Url;
-use hyper::header::{Authorization, ContentType, Headers};
+use hyper::header::{ContentType, Headers};
 use hyper::mime::{Attr, Mime, SubLevel, TopLevel, Value};
 use serde_json;
 
+use auth::Auth;
 use bitbucket_data::{PullRequest, PullRequestList, UserSearchResult};
 use config::Project;
 use error::{Error, ErrorKind, Result};
+use forge::{get_self_url, Forge};
 
 pub struct Bitbucket {
     client: Client,
@@ -17,23 +19,25 @@ pub struct Bitbucket {
 }
 
 impl Bitbucket {
-    pub fn new(auth: String, base_url: String) -> Result<Bitbucket> {
+    pub fn new(auth: Auth, base_url: String, client: Client) -> Result<Bitbucket> {
         let url = Url::parse(base_url.as_str())?;
         let mut headers = Headers::new();
-        headers.set(Authorization(format!("Basic {}", auth).to_owned()));
+        headers.set(auth.header());
         headers.set(ContentType(Mime(
             TopLevel::Application,
             SubLevel::Json,
             vec![(Attr::Charset, Value::Utf8)],
         )));
         Ok(Bitbucket {
-            client: Client::new(),
+            client: client,
             headers: headers,
             base_url: url,
         })
     }
+}
 
-    pub fn branch_exists(&self, project: &Project, branch: &str, debug: bool) -> Result<bool> {
+impl Forge for Bitbucket {
+    fn branch_exists(&self, project: &Project, branch: &str, debug: bool) -> Result<bool> {
         /*
          * So bitbucket server is really annoying in that they don't let you
          * search for full ref names. Searching for a commit with the ref ends
@@ -59,7 +63,7 @@ impl Bitbucket {
         Ok(res.status.is_success())
     }
 
-    pub fn create_pull_request(
+    fn create_pull_request(
         &self,
         pull_request: &PullRequest,
         dry: bool,
@@ -105,7 +109,7 @@ impl Bitbucket {
         }
     }
 
-    pub fn list_pull_requests(&self, debug: bool, role: &str) -> Result<PullRequestList> {
+    fn list_pull_requests(&self, debug: bool, role: &str) -> Result<PullRequestList> {
         let mut url = self.base_url.join("rest/api/1.0/dashboard/pull-requests")?;
         url.query_pairs_mut().append_pair("state", "OPEN");
 
@@ -134,7 +138,7 @@ impl Bitbucket {
         }
     }
 
-    pub fn user(&self, filter: &str, debug: bool) -> Result<UserSearchResult> {
+    fn user_search(&self, filter: &str, debug: bool) -> Result<UserSearchResult> {
         let mut url = self.base_url.join("rest/api/1.0/users")?;
         url.query_pairs_mut().append_pair("filter", filter);
 
@@ -156,13 +160,189 @@ impl Bitbucket {
             Err(ErrorKind::RequestError(response_body).into())
         }
     }
-}
 
-fn get_self_url(pull_request: &PullRequest) -> Result<Url> {
-    if let Some(link) = pull_request.self_link() {
-        let url = Url::parse(&link)?;
-        Ok(url)
-    } else {
-        Err(ErrorKind::MissingSelfLink.into())
+    fn get_pull_request(&self, project: &Project, pr_id: u64, debug: bool) -> Result<PullRequest> {
+        let component = format!(
+            "rest/api/1.0/projects/{}/repos/{}/pull-requests/{}",
+            project.target_project, project.target_slug, pr_id
+        );
+        let url = self.base_url.join(&component)?;
+
+        if debug {
+            println!("{}", url);
+        }
+
+        let mut res = self.client.get(url).headers(self.headers.clone()).send()?;
+        let mut response_body = String::new();
+        res.read_to_string(&mut response_body)?;
+        if res.status.is_success() {
+            if debug {
+                println!("{}", response_body);
+            }
+            let res = serde_json::from_str(response_body.as_str())?;
+            Ok(res)
+        } else {
+            Err(ErrorKind::RequestError(response_body).into())
+        }
+    }
+
+    fn merge_pull_request(
+        &self,
+        project: &Project,
+        pr_id: u64,
+        strategy: &str,
+        version: u64,
+        debug: bool,
+    ) -> Result<Url> {
+        let component = format!(
+            "rest/api/1.0/projects/{}/repos/{}/pull-requests/{}/merge",
+            project.target_project, project.target_slug, pr_id
+        );
+        let mut url = self.base_url.join(&component)?;
+        // The server rejects the merge unless we echo back the version we last
+        // saw, so it can detect a concurrent update.
+        url.query_pairs_mut()
+            .append_pair("version", &version.to_string());
+        let body = format!("{{\"strategyId\":\"{}\"}}", strategy);
+
+        if debug {
+            println!("{} {}", url, body);
+        }
+
+        let mut res = self.client
+            .post(url)
+            .headers(self.headers.clone())
+            .body(body.as_str())
+            .send()?;
+        let mut response_body = String::new();
+        res.read_to_string(&mut response_body)?;
+
+        if res.status.is_success() {
+            if debug {
+                println!("{}", response_body);
+            }
+            let data = serde_json::from_str(response_body.as_str())?;
+            get_self_url(&data)
+        } else {
+            Err(ErrorKind::RequestError(response_body).into())
+        }
+    }
+
+    fn set_approval(
+        &self,
+        project: &Project,
+        pr_id: u64,
+        user: &str,
+        approved: bool,
+        debug: bool,
+    ) -> Result<()> {
+        let component = format!(
+            "rest/api/1.0/projects/{}/repos/{}/pull-requests/{}/participants/{}",
+            project.target_project, project.target_slug, pr_id, user
+        );
+        let url = self.base_url.join(&component)?;
+        let status = if approved { "APPROVED" } else { "UNAPPROVED" };
+        let body = format!("{{\"status\":\"{}\"}}", status);
+
+        if debug {
+            println!("{} {}", url, body);
+        }
+
+        let mut res = self.client
+            .put(url)
+            .headers(self.headers.clone())
+            .body(body.as_str())
+            .send()?;
+        let mut response_body = String::new();
+        res.read_to_string(&mut response_body)?;
+
+        if res.status.is_success() {
+            if debug {
+                println!("{}", response_body);
+            }
+            Ok(())
+        } else {
+            Err(ErrorKind::RequestError(response_body).into())
+        }
     }
+
+    fn decline_pull_request(
+        &self,
+        project: &Project,
+        pr_id: u64,
+        version: u64,
+        debug: bool,
+    ) -> Result<()> {
+        let component = format!(
+            "rest/api/1.0/projects/{}/repos/{}/pull-requests/{}/decline",
+            project.target_project, project.target_slug, pr_id
+        );
+        let mut url = self.base_url.join(&component)?;
+        url.query_pairs_mut()
+            .append_pair("version", &version.to_string());
+
+        if debug {
+            println!("{}", url);
+        }
+
+        let mut res = self.client
+            .post(url)
+            .headers(self.headers.clone())
+            .body("")
+            .send()?;
+        let mut response_body = String::new();
+        res.read_to_string(&mut response_body)?;
+
+        if res.status.is_success() {
+            if debug {
+                println!("{}", response_body);
+            }
+            Ok(())
+        } else {
+            Err(ErrorKind::RequestError(response_body).into())
+        }
+    }
+
+    fn comment_pull_request(
+        &self,
+        project: &Project,
+        pr_id: u64,
+        text: &str,
+        debug: bool,
+    ) -> Result<()> {
+        let component = format!(
+            "rest/api/1.0/projects/{}/repos/{}/pull-requests/{}/comments",
+            project.target_project, project.target_slug, pr_id
+        );
+        let url = self.base_url.join(&component)?;
+        let body = comment_body(text)?;
+
+        if debug {
+            println!("{} {}", url, body);
+        }
+
+        let mut res = self.client
+            .post(url)
+            .headers(self.headers.clone())
+            .body(body.as_str())
+            .send()?;
+        let mut response_body = String::new();
+        res.read_to_string(&mut response_body)?;
+
+        if res.status.is_success() {
+            if debug {
+                println!("{}", response_body);
+            }
+            Ok(())
+        } else {
+            Err(ErrorKind::RequestError(response_body).into())
+        }
+    }
+}
+
+/// Build a Bitbucket Server comment body, escaping the text through serde.
+fn comment_body(text: &str) -> Result<String> {
+    let mut map = serde_json::Map::new();
+    map.insert("text".to_string(), serde_json::Value::String(text.to_string()));
+    Ok(serde_json::to_string(&serde_json::Value::Object(map))?)
 }
@@ -0,0 +1,459 @@
+use std::io::Read;
+
+use hyper::Client;
+use hyper::Url;
+use hyper::header::{ContentType, Headers};
+use hyper::mime::{Attr, Mime, SubLevel, TopLevel, Value};
+use serde_json;
+
+use auth::Auth;
+use bitbucket_data::{PullRequest, PullRequestList, UserSearchResult};
+use config::Project;
+use error::{Error, ErrorKind, Result};
+use forge::Forge;
+
+/// Bitbucket Cloud backend.
+///
+/// Cloud lives under `2.0/repositories/{workspace}/{repo}` and returns a
+/// different pull-request schema than Server: the canonical URL is nested at
+/// `links.self.href` (an object) rather than Server's `links.self[]` array.
+/// Read paths rewrite each pull-request document into the Server shape with
+/// [`normalize_pull_request`] before deserializing into the shared
+/// `bitbucket_data` types, and create/merge read the created resource's URL
+/// directly with [`cloud_self_url`], so the `pr`/`merge` flows stay
+/// backend-agnostic.
+pub struct Cloud {
+    client: Client,
+    headers: Headers,
+    base_url: Url,
+}
+
+impl Cloud {
+    pub fn new(auth: Auth, base_url: String, client: Client) -> Result<Cloud> {
+        let url = Url::parse(base_url.as_str())?;
+        let mut headers = Headers::new();
+        headers.set(auth.header());
+        headers.set(ContentType(Mime(
+            TopLevel::Application,
+            SubLevel::Json,
+            vec![(Attr::Charset, Value::Utf8)],
+        )));
+        Ok(Cloud {
+            client: client,
+            headers: headers,
+            base_url: url,
+        })
+    }
+
+    /// Resolve the authenticated user's `account_id` via `2.0/user`.
+    ///
+    /// Cloud keys its involvement feed by account rather than offering a
+    /// caller-agnostic dashboard, so listing PRs needs the current account up
+    /// front.
+    fn current_account_id(&self, debug: bool) -> Result<String> {
+        let url = self.base_url.join("2.0/user")?;
+
+        let mut res = self.client.get(url).headers(self.headers.clone()).send()?;
+        let mut response_body = String::new();
+        res.read_to_string(&mut response_body)?;
+
+        if debug {
+            println!("{}", response_body);
+        }
+
+        if !res.status.is_success() {
+            return Err(ErrorKind::RequestError(response_body).into());
+        }
+
+        let data: serde_json::Value = serde_json::from_str(response_body.as_str())?;
+        data.get("account_id")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .ok_or::<Error>(
+                ErrorKind::RequestError("2.0/user response missing account_id".to_string()).into(),
+            )
+    }
+}
+
+impl Forge for Cloud {
+    fn branch_exists(&self, project: &Project, branch: &str, debug: bool) -> Result<bool> {
+        let component = format!(
+            "2.0/repositories/{}/{}/refs/branches/{}",
+            project.target_project, project.target_slug, branch
+        );
+        let url = self.base_url.join(&component)?;
+
+        let mut res = self.client.get(url).headers(self.headers.clone()).send()?;
+        let mut response_body = String::new();
+        res.read_to_string(&mut response_body)?;
+
+        if debug {
+            println!("{}", response_body);
+        }
+
+        Ok(res.status.is_success())
+    }
+
+    fn create_pull_request(
+        &self,
+        pull_request: &PullRequest,
+        dry: bool,
+        debug: bool,
+    ) -> Result<Url> {
+        let component = format!(
+            "2.0/repositories/{}/{}/pullrequests",
+            pull_request
+                .project()
+                .ok_or::<Error>(ErrorKind::InvalidPullRequest("Missing toRef".to_string()).into())?,
+            pull_request
+                .slug()
+                .ok_or::<Error>(ErrorKind::InvalidPullRequest("Missing target slug".to_string()).into())?
+        );
+        let url = self.base_url.join(&component)?;
+        // Server's `PullRequest` serializes to the `fromRef`/`toRef` shape used
+        // by `client.rs`; Cloud wants `source`/`destination` branch objects and
+        // account-id reviewers, so build the body explicitly here.
+        let body = cloud_pull_request_body(pull_request)?;
+
+        if debug {
+            println!("{}", body);
+        }
+
+        if dry {
+            println!("Dry run: \"{}\"", body);
+            return Err(ErrorKind::DryRun.into());
+        }
+
+        let mut res = self.client
+            .post(url)
+            .headers(self.headers.clone())
+            .body(body.as_str())
+            .send()?;
+        let mut response_body = String::new();
+        res.read_to_string(&mut response_body)?;
+
+        if res.status.is_success() {
+            if debug {
+                println!("{}", response_body);
+            }
+            cloud_self_url(response_body.as_str())
+        } else {
+            Err(ErrorKind::RequestError(response_body).into())
+        }
+    }
+
+    fn list_pull_requests(&self, debug: bool, _role: &str) -> Result<PullRequestList> {
+        // Cloud has no top-level PR collection; the involvement feed that
+        // mirrors Server's `dashboard/pull-requests` lives under
+        // `2.0/pullrequests/{selected_user}`, keyed by the authenticated
+        // account. Push the account id as a path segment so its `:`-laden form
+        // is percent-encoded rather than splitting the path.
+        let selected = self.current_account_id(debug)?;
+        let mut url = self.base_url.join("2.0/pullrequests")?;
+        url.path_segments_mut()
+            .map_err(|_| Error::from(ErrorKind::RequestError("cannot build pull-requests url".to_string())))?
+            .push(&selected);
+        url.query_pairs_mut().append_pair("state", "OPEN");
+
+        // Unlike Server's `dashboard/pull-requests`, the Cloud involvement feed
+        // returns every pull request the selected account touches in any role
+        // and honors no `role` query parameter, so `--role` filtering is not
+        // supported here and `_role` is intentionally ignored.
+
+        if debug {
+            println!("{}", url);
+        }
+
+        let mut res = self.client.get(url).headers(self.headers.clone()).send()?;
+
+        let mut response_body = String::new();
+        res.read_to_string(&mut response_body)?;
+        if res.status.is_success() {
+            if debug {
+                println!("{}", response_body);
+            }
+            let mut data: serde_json::Value = serde_json::from_str(response_body.as_str())?;
+            if let Some(values) = data.get_mut("values").and_then(|v| v.as_array_mut()) {
+                for pr in values.iter_mut() {
+                    normalize_pull_request(pr);
+                }
+            }
+            let res = serde_json::from_value(data)?;
+            Ok(res)
+        } else {
+            Err(ErrorKind::RequestError(response_body).into())
+        }
+    }
+
+    fn user_search(&self, _filter: &str, _debug: bool) -> Result<UserSearchResult> {
+        // Bitbucket Cloud exposes no user-directory search: `2.0/users/{id}` is
+        // a single-account lookup, not a query endpoint, and there is no public
+        // directory to fuzzy-match against. The interactive reviewer picker is
+        // therefore Server-only; fail loudly rather than silently returning no
+        // candidates.
+        Err(ErrorKind::RequestError(
+            "user search is not supported on Bitbucket Cloud; specify reviewers explicitly".to_string(),
+        ).into())
+    }
+
+    fn get_pull_request(&self, project: &Project, pr_id: u64, debug: bool) -> Result<PullRequest> {
+        let component = format!(
+            "2.0/repositories/{}/{}/pullrequests/{}",
+            project.target_project, project.target_slug, pr_id
+        );
+        let url = self.base_url.join(&component)?;
+
+        if debug {
+            println!("{}", url);
+        }
+
+        let mut res = self.client.get(url).headers(self.headers.clone()).send()?;
+        let mut response_body = String::new();
+        res.read_to_string(&mut response_body)?;
+        if res.status.is_success() {
+            if debug {
+                println!("{}", response_body);
+            }
+            let mut data: serde_json::Value = serde_json::from_str(response_body.as_str())?;
+            normalize_pull_request(&mut data);
+            let res = serde_json::from_value(data)?;
+            Ok(res)
+        } else {
+            Err(ErrorKind::RequestError(response_body).into())
+        }
+    }
+
+    fn merge_pull_request(
+        &self,
+        project: &Project,
+        pr_id: u64,
+        strategy: &str,
+        _version: u64,
+        debug: bool,
+    ) -> Result<Url> {
+        // Cloud has no optimistic-concurrency version on the merge call; it
+        // takes the strategy in the body and 409s on its own if the PR moved.
+        let component = format!(
+            "2.0/repositories/{}/{}/pullrequests/{}/merge",
+            project.target_project, project.target_slug, pr_id
+        );
+        let url = self.base_url.join(&component)?;
+        let body = format!("{{\"merge_strategy\":\"{}\"}}", strategy);
+
+        if debug {
+            println!("{} {}", url, body);
+        }
+
+        let mut res = self.client
+            .post(url)
+            .headers(self.headers.clone())
+            .body(body.as_str())
+            .send()?;
+        let mut response_body = String::new();
+        res.read_to_string(&mut response_body)?;
+
+        if res.status.is_success() {
+            if debug {
+                println!("{}", response_body);
+            }
+            cloud_self_url(response_body.as_str())
+        } else {
+            Err(ErrorKind::RequestError(response_body).into())
+        }
+    }
+
+    fn set_approval(
+        &self,
+        project: &Project,
+        pr_id: u64,
+        _user: &str,
+        approved: bool,
+        debug: bool,
+    ) -> Result<()> {
+        // Cloud approves on behalf of the authenticated user via a dedicated
+        // sub-resource rather than a participant status field.
+        let component = format!(
+            "2.0/repositories/{}/{}/pullrequests/{}/approve",
+            project.target_project, project.target_slug, pr_id
+        );
+        let url = self.base_url.join(&component)?;
+
+        if debug {
+            println!("{} approved={}", url, approved);
+        }
+
+        let builder = if approved {
+            self.client.post(url)
+        } else {
+            self.client.delete(url)
+        };
+        let mut res = builder.headers(self.headers.clone()).body("").send()?;
+        let mut response_body = String::new();
+        res.read_to_string(&mut response_body)?;
+
+        if res.status.is_success() {
+            if debug {
+                println!("{}", response_body);
+            }
+            Ok(())
+        } else {
+            Err(ErrorKind::RequestError(response_body).into())
+        }
+    }
+
+    fn decline_pull_request(
+        &self,
+        project: &Project,
+        pr_id: u64,
+        _version: u64,
+        debug: bool,
+    ) -> Result<()> {
+        let component = format!(
+            "2.0/repositories/{}/{}/pullrequests/{}/decline",
+            project.target_project, project.target_slug, pr_id
+        );
+        let url = self.base_url.join(&component)?;
+
+        if debug {
+            println!("{}", url);
+        }
+
+        let mut res = self.client
+            .post(url)
+            .headers(self.headers.clone())
+            .body("")
+            .send()?;
+        let mut response_body = String::new();
+        res.read_to_string(&mut response_body)?;
+
+        if res.status.is_success() {
+            if debug {
+                println!("{}", response_body);
+            }
+            Ok(())
+        } else {
+            Err(ErrorKind::RequestError(response_body).into())
+        }
+    }
+
+    fn comment_pull_request(
+        &self,
+        project: &Project,
+        pr_id: u64,
+        text: &str,
+        debug: bool,
+    ) -> Result<()> {
+        let component = format!(
+            "2.0/repositories/{}/{}/pullrequests/{}/comments",
+            project.target_project, project.target_slug, pr_id
+        );
+        let url = self.base_url.join(&component)?;
+        let body = comment_body(text)?;
+
+        if debug {
+            println!("{} {}", url, body);
+        }
+
+        let mut res = self.client
+            .post(url)
+            .headers(self.headers.clone())
+            .body(body.as_str())
+            .send()?;
+        let mut response_body = String::new();
+        res.read_to_string(&mut response_body)?;
+
+        if res.status.is_success() {
+            if debug {
+                println!("{}", response_body);
+            }
+            Ok(())
+        } else {
+            Err(ErrorKind::RequestError(response_body).into())
+        }
+    }
+}
+
+/// Rewrite a single Cloud pull-request document in place into the Server-shaped
+/// JSON that `bitbucket_data::PullRequest` deserializes.
+///
+/// The one structural divergence the shared type trips on is the self link:
+/// Cloud nests it at `links.self.href` (an object), while Server exposes
+/// `links.self` as an array of `{href}`. Wrap the object in a single-element
+/// array so the deserializer finds the link where it expects it.
+fn normalize_pull_request(pr: &mut serde_json::Value) {
+    if let Some(links) = pr.get_mut("links") {
+        if let Some(self_link) = links.get("self").cloned() {
+            if self_link.is_object() {
+                links["self"] = serde_json::Value::Array(vec![self_link]);
+            }
+        }
+    }
+}
+
+/// Extract Cloud's canonical pull-request URL from a create/merge response.
+///
+/// Cloud returns it at `links.self.href`; reading it here avoids routing the
+/// response through Server's `PullRequest::self_link`, which expects the
+/// `links.self[]` array form and would otherwise yield `MissingSelfLink`.
+fn cloud_self_url(body: &str) -> Result<Url> {
+    let data: serde_json::Value = serde_json::from_str(body)?;
+    let href = data.get("links")
+        .and_then(|links| links.get("self"))
+        .and_then(|self_link| self_link.get("href"))
+        .and_then(|href| href.as_str())
+        .ok_or::<Error>(ErrorKind::MissingSelfLink.into())?;
+    Ok(Url::parse(href)?)
+}
+
+/// Serialize a pull request into Bitbucket Cloud's `pullrequests` POST shape.
+///
+/// Cloud diverges from Server's `fromRef`/`toRef` body: the source and
+/// destination each nest a `branch.name`, and reviewers are addressed by
+/// account id rather than `{user:{name}}`. Everything is routed through serde so
+/// titles and descriptions are escaped.
+fn cloud_pull_request_body(pull_request: &PullRequest) -> Result<String> {
+    let branch = |name: &str| {
+        let mut inner = serde_json::Map::new();
+        inner.insert("name".to_string(), serde_json::Value::String(name.to_string()));
+        let mut outer = serde_json::Map::new();
+        outer.insert("branch".to_string(), serde_json::Value::Object(inner));
+        serde_json::Value::Object(outer)
+    };
+
+    let source = pull_request
+        .from_branch()
+        .ok_or::<Error>(ErrorKind::InvalidPullRequest("Missing fromRef".to_string()).into())?;
+    let destination = pull_request
+        .to_branch()
+        .ok_or::<Error>(ErrorKind::InvalidPullRequest("Missing toRef".to_string()).into())?;
+
+    let reviewers: Vec<serde_json::Value> = pull_request
+        .reviewer_names()
+        .map(|id| {
+            let mut r = serde_json::Map::new();
+            r.insert("account_id".to_string(), serde_json::Value::String(id.to_string()));
+            serde_json::Value::Object(r)
+        })
+        .collect();
+
+    let mut map = serde_json::Map::new();
+    map.insert("title".to_string(), serde_json::Value::String(pull_request.title().to_string()));
+    map.insert(
+        "description".to_string(),
+        serde_json::Value::String(pull_request.description_text().to_string()),
+    );
+    map.insert("source".to_string(), branch(source));
+    map.insert("destination".to_string(), branch(destination));
+    map.insert("reviewers".to_string(), serde_json::Value::Array(reviewers));
+
+    Ok(serde_json::to_string(&serde_json::Value::Object(map))?)
+}
+
+/// Build a Bitbucket Cloud comment body (`content.raw`), escaping via serde.
+fn comment_body(text: &str) -> Result<String> {
+    let mut content = serde_json::Map::new();
+    content.insert("raw".to_string(), serde_json::Value::String(text.to_string()));
+    let mut map = serde_json::Map::new();
+    map.insert("content".to_string(), serde_json::Value::Object(content));
+    Ok(serde_json::to_string(&serde_json::Value::Object(map))?)
+}
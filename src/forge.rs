@@ -0,0 +1,83 @@
+use hyper::Url;
+
+use bitbucket_data::{PullRequest, PullRequestList, UserSearchResult};
+use config::Project;
+use error::{ErrorKind, Result};
+
+/// Backend-agnostic view of a hosted git forge.
+///
+/// Bitbucket Server and Bitbucket Cloud speak different REST dialects (`1.0`
+/// project/repo paths versus `2.0` workspace paths, and incompatible
+/// pull-request/account schemas), but the `pr` flow only needs the handful of
+/// operations below. Each supported backend provides one implementation and
+/// the caller picks between them via the `kind` field of the config.
+pub trait Forge {
+    /// Whether `branch` resolves to a ref on the target repository.
+    fn branch_exists(&self, project: &Project, branch: &str, debug: bool) -> Result<bool>;
+
+    /// Open a pull request and return the URL of the created resource.
+    fn create_pull_request(&self, pull_request: &PullRequest, dry: bool, debug: bool) -> Result<Url>;
+
+    /// List open pull requests visible to the authenticated user in `role`.
+    fn list_pull_requests(&self, debug: bool, role: &str) -> Result<PullRequestList>;
+
+    /// Search the user directory for accounts matching `filter`.
+    fn user_search(&self, filter: &str, debug: bool) -> Result<UserSearchResult>;
+
+    /// Fetch a single pull request, including its current `version` and the
+    /// merge strategies the server allows for its target branch.
+    fn get_pull_request(&self, project: &Project, pr_id: u64, debug: bool) -> Result<PullRequest>;
+
+    /// Merge `pr_id` with `strategy` at `version`, returning the URL of the
+    /// merged pull request (its `self` link) on success; the merge commit's own
+    /// URL is not returned. `version` is the value read back from
+    /// [`get_pull_request`] and is required by the API to guard against merging
+    /// a pull request that changed underneath us.
+    fn merge_pull_request(
+        &self,
+        project: &Project,
+        pr_id: u64,
+        strategy: &str,
+        version: u64,
+        debug: bool,
+    ) -> Result<Url>;
+
+    /// Record or withdraw the given user's approval of a pull request.
+    fn set_approval(
+        &self,
+        project: &Project,
+        pr_id: u64,
+        user: &str,
+        approved: bool,
+        debug: bool,
+    ) -> Result<()>;
+
+    /// Decline a pull request at its current `version`.
+    fn decline_pull_request(
+        &self,
+        project: &Project,
+        pr_id: u64,
+        version: u64,
+        debug: bool,
+    ) -> Result<()>;
+
+    /// Add a comment to a pull request.
+    fn comment_pull_request(
+        &self,
+        project: &Project,
+        pr_id: u64,
+        text: &str,
+        debug: bool,
+    ) -> Result<()>;
+}
+
+/// Extract the `self` link of a freshly created pull request, shared by every
+/// backend that echoes the created resource back on success.
+pub fn get_self_url(pull_request: &PullRequest) -> Result<Url> {
+    if let Some(link) = pull_request.self_link() {
+        let url = Url::parse(&link)?;
+        Ok(url)
+    } else {
+        Err(ErrorKind::MissingSelfLink.into())
+    }
+}
@@ -0,0 +1,107 @@
+//! A small fuzzy subsequence scorer used by the interactive reviewer picker.
+//!
+//! A query matches a candidate when its characters appear in order (not
+//! necessarily adjacent). Matches that land after a separator (`.`/`_`/`-`) or
+//! on a case boundary score higher, and a gap between consecutive matched
+//! characters is penalized in proportion to its size, so "jd" ranks
+//! `john.doe` above `jedediah`.
+
+/// Points awarded for a single matched character.
+const MATCH: i32 = 16;
+/// Extra points when a match follows a separator, rewarding word starts.
+const SEPARATOR_BONUS: i32 = 8;
+/// Extra points when a match lands on a lower→upper case boundary.
+const CAMEL_BONUS: i32 = 8;
+/// Points subtracted per skipped character between two matches.
+const GAP_PENALTY: i32 = 2;
+
+fn is_separator(c: char) -> bool {
+    c == '.' || c == '_' || c == '-'
+}
+
+/// Score `candidate` against `query`, returning `None` when `query` is not a
+/// subsequence of `candidate` and `Some(score)` otherwise. Higher is better.
+pub fn score(query: &str, candidate: &str) -> Option<i32> {
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let cand: Vec<char> = candidate.chars().collect();
+    let mut wanted = query.chars().flat_map(char::to_lowercase);
+    let mut next = wanted.next();
+    let mut total = 0;
+    let mut last_match: Option<usize> = None;
+
+    for (i, &c) in cand.iter().enumerate() {
+        let target = match next {
+            Some(t) => t,
+            None => break,
+        };
+        if c.to_lowercase().eq(target.to_lowercase()) {
+            let mut points = MATCH;
+            if i == 0 || is_separator(cand[i - 1]) {
+                points += SEPARATOR_BONUS;
+            }
+            if i > 0 && cand[i - 1].is_lowercase() && c.is_uppercase() {
+                points += CAMEL_BONUS;
+            }
+            if let Some(prev) = last_match {
+                points -= (i - prev - 1) as i32 * GAP_PENALTY;
+            }
+            total += points;
+            last_match = Some(i);
+            next = wanted.next();
+        }
+    }
+
+    if next.is_none() {
+        Some(total)
+    } else {
+        None
+    }
+}
+
+/// Filter `candidates` to those matching `query` and return them paired with
+/// their score, sorted by descending score (ties keep input order).
+pub fn rank<'a>(query: &str, candidates: &'a [String]) -> Vec<(&'a String, i32)> {
+    let mut scored: Vec<(&String, i32)> = candidates
+        .iter()
+        .filter_map(|c| score(query, c).map(|s| (c, s)))
+        .collect();
+    scored.sort_by(|a, b| b.1.cmp(&a.1));
+    scored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rank, score};
+
+    #[test]
+    fn matches_and_rejects_subsequences() {
+        assert!(score("jdoe", "john.doe").is_some());
+        assert!(score("xyz", "john.doe").is_none());
+    }
+
+    #[test]
+    fn empty_query_scores_zero() {
+        assert_eq!(score("", "anyone"), Some(0));
+    }
+
+    #[test]
+    fn surfaces_dotted_name() {
+        // "jdoe" is a subsequence of john.doe but not of jedediah (no 'o'),
+        // which is the whole point of the picker's contract.
+        assert!(score("jdoe", "john.doe").is_some());
+        assert!(score("jdoe", "jedediah").is_none());
+    }
+
+    #[test]
+    fn ranks_word_start_match_above_dense_match() {
+        let candidates = vec!["john.doe".to_string(), "jedediah".to_string()];
+        let ranked = rank("jd", &candidates);
+        assert_eq!(ranked.len(), 2);
+        // The `d` in john.doe follows a separator, earning a bonus that lifts it
+        // above jedediah's denser but unanchored match.
+        assert_eq!(ranked[0].0.as_str(), "john.doe");
+    }
+}
@@ -4,10 +4,14 @@
 extern crate clap;
 #[macro_use]
 extern crate error_chain;
+extern crate crypto;
 extern crate eprompt;
 extern crate git2;
 extern crate hyper;
+extern crate hyper_native_tls;
+extern crate native_tls;
 extern crate prettytable;
+extern crate rand;
 extern crate rpassword;
 extern crate rustc_serialize;
 extern crate url;
@@ -23,16 +27,25 @@ use std::path::Path;
 use eprompt::Prompt;
 use rustc_serialize::base64::{ToBase64, STANDARD};
 
+use auth::Auth;
 use client::Bitbucket;
-use config::Config;
+use cloud::Cloud;
+use config::{Config, Project};
 use error::{Error, ErrorKind, Result, UnwrapOrExit};
+use forge::Forge;
 use pull_request::PullRequest;
 
+mod auth;
 mod client;
+mod cloud;
 mod config;
 mod error;
+mod forge;
+mod fuzzy;
 mod git;
 mod pull_request;
+mod secret;
+mod tls;
 mod util;
 
 pub fn exit(message: &str) -> ! {
@@ -50,8 +63,34 @@ fn prompt(label: &str) -> Result<String> {
 
 fn setup(path: &Path) -> Result<()> {
     let server = prompt("bitbucket server url: ")?;
-    let username = prompt("username: ")?;
-    let password = rpassword::prompt_password_stdout("password: ")?;
+
+    println!("
+Choose how to authenticate. \"basic\" stores a base64-encoded
+username:password; \"token\" stores an HTTP/personal access token sent as a
+Bearer credential (recommended where basic auth is locked down).");
+    let auth_mode = prompt("auth mode [basic/token]: ")?;
+
+    let auth = if auth_mode.trim().eq_ignore_ascii_case("token") {
+        let token = rpassword::prompt_password_stdout("access token: ")?;
+        Auth::Bearer(token.trim().to_string())
+    } else {
+        let username = prompt("username: ")?;
+        let password = rpassword::prompt_password_stdout("password: ")?;
+        let basic = format!("{}:{}", username.trim(), password.trim());
+        Auth::Basic(basic.as_bytes().to_base64(STANDARD))
+    };
+
+    println!("
+Credentials can be encrypted at rest with a passphrase instead of stored
+as reversible base64. Unattended runs then read the passphrase from the
+BB_PASSPHRASE environment variable.");
+    let encrypt = prompt("encrypt credentials? [y/N]: ")?;
+    let encrypted = if encrypt.trim().eq_ignore_ascii_case("y") {
+        let passphrase = rpassword::prompt_password_stdout("passphrase: ")?;
+        Some(secret::seal(passphrase.trim(), auth.secret())?)
+    } else {
+        None
+    };
 
     println!("
 The project name should be the same name as the repo basename (directory).
@@ -84,12 +123,18 @@ The target branch is the branch to which the pull request will be made.
 This can be overwritten on the command line.");
     let target_branch = prompt("target branch: ")?;
 
-    let auth = format!("{}:{}", username.trim(), password.trim());
-    let base64auth = auth.as_bytes().to_base64(STANDARD);
+    // When sealing, keep the plaintext secret out of the config entirely; only
+    // the scheme is persisted in the clear so the sealed blob can be reattached.
+    let stored_auth = if encrypted.is_some() {
+        auth.with_secret(String::new())
+    } else {
+        auth.clone()
+    };
 
     Config::create_file(path,
                         &server,
-                        &base64auth,
+                        &stored_auth,
+                        encrypted.as_ref(),
                         &project_name,
                         &source_project,
                         &source_slug,
@@ -104,6 +149,40 @@ Please edit {} to have your desired configuration (particularly user groups)",
     Ok(())
 }
 
+/// Resolve the credential to authenticate with, decrypting it when the config
+/// stores a sealed credential. The passphrase comes from `BB_PASSPHRASE` for
+/// unattended runs, falling back to an interactive prompt.
+fn resolve_auth(config: &Config) -> Result<Auth> {
+    match config.encrypted {
+        Some(ref enc) => {
+            let passphrase = match env::var("BB_PASSPHRASE") {
+                Ok(ref p) if !p.trim().is_empty() => p.trim().to_string(),
+                _ => rpassword::prompt_password_stdout("passphrase: ")?.trim().to_string(),
+            };
+            let plaintext = secret::open(&passphrase, enc)?;
+            Ok(config.credential().with_secret(plaintext))
+        }
+        None => Ok(config.credential()),
+    }
+}
+
+/// Resolve the target project for a subcommand, honoring the global
+/// `--project`/`--repo` overrides and otherwise falling back to directory-name
+/// auto-detection. `--project` selects a configured project by name and
+/// `--repo` overrides its target repository slug, so a project can be targeted
+/// without `cd`-ing into a matching directory.
+fn resolve_project(config: &Config, matches: &ArgMatches) -> Result<Project> {
+    let name = match matches.value_of("project") {
+        Some(name) => name.to_string(),
+        None => util::get_project_name()?,
+    };
+    let mut project = config.get_project(&name)?;
+    if let Some(repo) = matches.value_of("repo") {
+        project.target_slug = repo.to_string();
+    }
+    Ok(project)
+}
+
 fn groups(config: &Config) -> Result<()> {
     for (name, group) in &config.groups {
         println!("{}: {:?}", name, group);
@@ -111,7 +190,7 @@ fn groups(config: &Config) -> Result<()> {
     Ok(())
 }
 
-fn pr(config: &Config, client: &Bitbucket, matches: &ArgMatches) -> Result<()> {
+fn pr(config: &Config, client: &Forge, matches: &ArgMatches) -> Result<()> {
     let debug = matches.is_present("debug");
 
     let subcmd = matches.subcommand_matches("pr")
@@ -119,7 +198,7 @@ fn pr(config: &Config, client: &Bitbucket, matches: &ArgMatches) -> Result<()> {
 
     let dry = subcmd.is_present("dry_run");
 
-    let project = config.get_project(&util::get_project_name()?)?;
+    let project = resolve_project(config, matches)?;
 
     let title = subcmd.value_of("title").unwrap(); // This is safe since it's required
     let mut description = subcmd.value_of("description").unwrap_or("").to_string();
@@ -140,6 +219,13 @@ fn pr(config: &Config, client: &Bitbucket, matches: &ArgMatches) -> Result<()> {
         for reviewer in reviewer_list {
             reviewers.insert(reviewer.to_string());
         }
+    } else if subcmd.is_present("interactive") && !subcmd.is_present("group") {
+        reviewers = &reviewers | &pick_reviewers(client, debug)?;
+        if let Some(appended) = subcmd.values_of("append") {
+            for append in appended {
+                reviewers.insert(append.to_string());
+            }
+        }
     } else {
         if let Some(groups) = subcmd.values_of("group") {
             for group in groups {
@@ -172,6 +258,146 @@ fn pr(config: &Config, client: &Bitbucket, matches: &ArgMatches) -> Result<()> {
     Ok(())
 }
 
+/// Interactively search the user directory and multi-select reviewers.
+///
+/// Each round reads a query, hits `user_search` with it, fuzzy-ranks the
+/// returned accounts so close matches float to the top, and lets the user pick
+/// by index. Repeats until an empty query is entered.
+fn pick_reviewers(client: &Forge, debug: bool) -> Result<HashSet<String>> {
+    let mut selected: HashSet<String> = HashSet::new();
+
+    loop {
+        let query = prompt("reviewer search (blank to finish): ")?;
+        if query.is_empty() {
+            break;
+        }
+
+        let names = client.user_search(&query, debug)?.usernames();
+        let ranked = fuzzy::rank(&query, &names);
+        if ranked.is_empty() {
+            println!("  no matches");
+            continue;
+        }
+
+        for (i, &(name, score)) in ranked.iter().enumerate() {
+            println!("  [{}] {} ({})", i, name, score);
+        }
+
+        let choice = prompt("select numbers (comma-separated, blank to search again): ")?;
+        for part in choice.split(',') {
+            let part = part.trim();
+            if part.is_empty() {
+                continue;
+            }
+            match part.parse::<usize>() {
+                Ok(idx) => match ranked.get(idx) {
+                    Some(&(name, _)) => {
+                        selected.insert(name.clone());
+                    }
+                    None => println!("  no candidate at [{}]", idx),
+                },
+                Err(_) => println!("  not a number: {}", part),
+            }
+        }
+    }
+
+    Ok(selected)
+}
+
+fn merge(config: &Config, client: &Forge, matches: &ArgMatches) -> Result<()> {
+    let debug = matches.is_present("debug");
+
+    let subcmd = matches.subcommand_matches("merge")
+        .ok_or::<Error>(ErrorKind::MissingSubcommand("merge".to_string()).into())?;
+
+    let project = resolve_project(config, matches)?;
+
+    let pr_id = value_t!(subcmd, "id", u64).unwrap_or_else(|e| e.exit());
+
+    // GET the PR first: we need its current version (to guard the merge) and
+    // the strategies the server allows for this target branch.
+    let pull_request = client.get_pull_request(&project, pr_id, debug)?;
+    let version = pull_request.version();
+    let allowed = pull_request.merge_strategies();
+
+    let strategy = match subcmd.value_of("strategy") {
+        Some(requested) => {
+            if !allowed.iter().any(|s| s == requested) {
+                return Err(ErrorKind::InvalidMergeStrategy(requested.to_string(), allowed).into());
+            }
+            requested.to_string()
+        }
+        None => pull_request
+            .default_merge_strategy()
+            .ok_or::<Error>(ErrorKind::InvalidMergeStrategy("<none>".to_string(), allowed).into())?,
+    };
+
+    let url = client.merge_pull_request(&project, pr_id, &strategy, version, debug)?;
+
+    println!("Merged pull request: {}", url.as_str());
+
+    Ok(())
+}
+
+fn approve(config: &Config, client: &Forge, matches: &ArgMatches, approved: bool) -> Result<()> {
+    let debug = matches.is_present("debug");
+    let name = if approved { "approve" } else { "unapprove" };
+
+    let subcmd = matches.subcommand_matches(name)
+        .ok_or::<Error>(ErrorKind::MissingSubcommand(name.to_string()).into())?;
+
+    let project = resolve_project(config, matches)?;
+    let pr_id = value_t!(subcmd, "id", u64).unwrap_or_else(|e| e.exit());
+    let user = subcmd.value_of("user").unwrap_or(&config.username);
+
+    client.set_approval(&project, pr_id, user, approved, debug)?;
+
+    println!("{} pull request {}", if approved { "Approved" } else { "Unapproved" }, pr_id);
+
+    Ok(())
+}
+
+fn decline(config: &Config, client: &Forge, matches: &ArgMatches) -> Result<()> {
+    let debug = matches.is_present("debug");
+
+    let subcmd = matches.subcommand_matches("decline")
+        .ok_or::<Error>(ErrorKind::MissingSubcommand("decline".to_string()).into())?;
+
+    let project = resolve_project(config, matches)?;
+    let pr_id = value_t!(subcmd, "id", u64).unwrap_or_else(|e| e.exit());
+
+    // The API requires the version we last saw so it can reject a stale decline.
+    let version = client.get_pull_request(&project, pr_id, debug)?.version();
+    client.decline_pull_request(&project, pr_id, version, debug)?;
+
+    println!("Declined pull request {}", pr_id);
+
+    Ok(())
+}
+
+fn comment(config: &Config, client: &Forge, matches: &ArgMatches) -> Result<()> {
+    let debug = matches.is_present("debug");
+
+    let subcmd = matches.subcommand_matches("comment")
+        .ok_or::<Error>(ErrorKind::MissingSubcommand("comment".to_string()).into())?;
+
+    let project = resolve_project(config, matches)?;
+    let pr_id = value_t!(subcmd, "id", u64).unwrap_or_else(|e| e.exit());
+
+    // Fall back to the editor prompt (like `pr --long_description`) whenever a
+    // message isn't supplied inline, rather than posting an empty comment.
+    let text = match subcmd.value_of("message") {
+        Some(message) if !subcmd.is_present("long_description") => message.to_string(),
+        _ => Prompt::new().execute()?.trim().to_string(),
+    };
+
+    client.comment_pull_request(&project, pr_id, &text, debug)?;
+
+    println!("Added comment to pull request {}", pr_id);
+
+    Ok(())
+}
+
 fn main() {
     let default_config_path = env::home_dir().unwrap().join(".bb.yml");
     let yml = load_yaml!("app.yml");
@@ -183,6 +409,16 @@ fn main() {
             .short("c")
             .long("config")
             .global(true))
+        .arg(Arg::with_name("project")
+            .help("target a configured project by name instead of auto-detecting it")
+            .takes_value(true)
+            .long("project")
+            .global(true))
+        .arg(Arg::with_name("repo")
+            .help("override the target repository slug of the selected project")
+            .takes_value(true)
+            .long("repo")
+            .global(true))
         .get_matches();
 
     let config_file = matches.value_of("config").unwrap();
@@ -196,12 +432,27 @@ fn main() {
     }
 
     let config = Config::from_file(&config_path).unwrap_or_exit("Invalid config file");
-    let client = client::Bitbucket::new(config.auth.clone(), config.server.clone())
-        .unwrap_or_exit("Could not create client");
+    let auth = resolve_auth(&config).unwrap_or_exit("Could not unlock credentials");
+    let http = config.tls.build_client().unwrap_or_exit("Invalid TLS configuration");
+    let client: Box<Forge> = match config.kind {
+        config::Kind::Server => Box::new(
+            Bitbucket::new(auth.clone(), config.server.clone(), http)
+                .unwrap_or_exit("Could not create client"),
+        ),
+        config::Kind::Cloud => Box::new(
+            Cloud::new(auth.clone(), config.server.clone(), http)
+                .unwrap_or_exit("Could not create client"),
+        ),
+    };
 
     let res = match matches.subcommand_name() {
         Some("groups") => groups(&config),
-        Some("pr") => pr(&config, &client, &matches),
+        Some("pr") => pr(&config, client.as_ref(), &matches),
+        Some("merge") => merge(&config, client.as_ref(), &matches),
+        Some("approve") => approve(&config, client.as_ref(), &matches, true),
+        Some("unapprove") => approve(&config, client.as_ref(), &matches, false),
+        Some("decline") => decline(&config, client.as_ref(), &matches),
+        Some("comment") => comment(&config, client.as_ref(), &matches),
         _ => unreachable!(),
     };
 
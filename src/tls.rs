@@ -0,0 +1,58 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+use hyper::Client;
+use hyper::net::HttpsConnector;
+use hyper_native_tls::NativeTlsClient;
+use native_tls::{Certificate, TlsConnector};
+
+use error::{ErrorKind, Result};
+
+/// TLS options for reaching self-hosted servers.
+///
+/// A corporate or self-signed CA in front of Bitbucket Server makes the
+/// default client fail the handshake; pointing `ssl_cert` at the CA's PEM
+/// registers it as an additional trust root. `danger_accept_invalid_certs`
+/// disables verification entirely and should only be reached for as a last
+/// resort.
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    pub ssl_cert: Option<PathBuf>,
+    pub danger_accept_invalid_certs: bool,
+}
+
+impl TlsConfig {
+    /// Build an HTTP client honoring any custom CA or verification relaxation.
+    /// Returns a plain default client when nothing is configured, so the common
+    /// path pays no extra cost. Cert read/parse problems surface as
+    /// [`ErrorKind::Tls`] rather than an opaque handshake failure later.
+    pub fn build_client(&self) -> Result<Client> {
+        if self.ssl_cert.is_none() && !self.danger_accept_invalid_certs {
+            return Ok(Client::new());
+        }
+
+        let mut builder =
+            TlsConnector::builder().map_err(|e| ErrorKind::Tls(format!("{}", e)))?;
+
+        if let Some(ref path) = self.ssl_cert {
+            let mut pem = Vec::new();
+            File::open(path)
+                .and_then(|mut f| f.read_to_end(&mut pem))
+                .map_err(|e| ErrorKind::Tls(format!("{}: {}", path.display(), e)))?;
+            let cert =
+                Certificate::from_pem(&pem).map_err(|e| ErrorKind::Tls(format!("{}", e)))?;
+            builder
+                .add_root_certificate(cert)
+                .map_err(|e| ErrorKind::Tls(format!("{}", e)))?;
+        }
+
+        if self.danger_accept_invalid_certs {
+            builder.danger_accept_invalid_certs(true);
+        }
+
+        let connector = builder.build().map_err(|e| ErrorKind::Tls(format!("{}", e)))?;
+        let ssl = NativeTlsClient::from(connector);
+        Ok(Client::with_connector(HttpsConnector::new(ssl)))
+    }
+}